@@ -1,15 +1,92 @@
-use roc_parse::ast::Expr;
+use roc_parse::ast::{Base, Expr};
 use roc_repl_eval::{ReplApp, ReplAppMemory};
-use roc_std::RocStr;
-use roc_target::TargetInfo;
+use roc_target::{PtrWidth, TargetInfo};
 
 pub(crate) struct ExpectMemory {
     pub(crate) start: *const u8,
+    pub(crate) end: *const u8,
+    pub(crate) target_info: TargetInfo,
 }
 
+impl ExpectMemory {
+    /// Build an `ExpectMemory` bounded to the `len` bytes actually reserved for
+    /// this `expect`'s evaluation. Every deref is checked against this range, so
+    /// a corrupt pointer captured by a failing expect can't read outside of it.
+    pub(crate) fn new(start: *const u8, len: usize, target_info: TargetInfo) -> Self {
+        let end = unsafe { start.add(len) };
+
+        Self {
+            start,
+            end,
+            target_info,
+        }
+    }
+
+    /// The width (in bytes) of a pointer-sized field on the *target*, which may
+    /// differ from the host's `usize` if we're evaluating e.g. a 32-bit target
+    /// or the Wasm REPL on a 64-bit host.
+    fn ptr_width(&self) -> usize {
+        match self.target_info.ptr_width() {
+            PtrWidth::Bytes4 => 4,
+            PtrWidth::Bytes8 => 8,
+        }
+    }
+
+    /// Read a pointer-sized unsigned integer, sized according to the target
+    /// rather than the host's `usize`.
+    fn deref_target_usize(&self, addr: usize) -> usize {
+        match self.target_info.ptr_width() {
+            PtrWidth::Bytes4 => self.deref_u32(addr) as usize,
+            PtrWidth::Bytes8 => self.deref_u64(addr) as usize,
+        }
+    }
+
+    /// Checks whether `[addr, addr + width)` falls within `[start, end)`,
+    /// *without* ever forming a pointer outside of the allocation: `addr` and
+    /// `width` can come from a corrupt expect value, and `<*const T>::add`'s
+    /// contract makes an out-of-range offset UB even if it's never
+    /// dereferenced, so the check itself has to stay in integer arithmetic
+    /// until the range is proven valid.
+    fn check_bounds(&self, addr: usize, width: usize) -> Result<(), OutOfBounds> {
+        let start = self.start as usize;
+        let end = self.end as usize;
+
+        let region_start = start.checked_add(addr).ok_or(OutOfBounds)?;
+        let region_end = region_start.checked_add(width).ok_or(OutOfBounds)?;
+
+        if region_start >= start && region_end <= end {
+            Ok(())
+        } else {
+            Err(OutOfBounds)
+        }
+    }
+
+    /// Like `check_bounds`, but panics instead of returning a `Result`.
+    ///
+    /// Ideally a bad expect would render as a diagnostic instead of aborting
+    /// evaluation, but `ReplAppMemory`/`ReplApp` (defined in `roc_repl_eval`)
+    /// fix these methods' return types to bare values, so there's nowhere to
+    /// thread an `Option`/`Result` through at this layer without changing
+    /// that trait. This at least turns a corrupt pointer into a contained,
+    /// descriptive panic instead of UB.
+    fn assert_bounds(&self, addr: usize, width: usize) {
+        if self.check_bounds(addr, width).is_err() {
+            panic!(
+                "expect tried to read out-of-bounds memory at offset {addr} (width {width} \
+                 bytes); the failing expect likely captured a corrupt or dangling pointer"
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OutOfBounds;
+
 macro_rules! deref_number {
     ($name: ident, $t: ty) => {
         fn $name(&self, addr: usize) -> $t {
+            self.assert_bounds(addr, std::mem::size_of::<$t>());
+
             let ptr = unsafe { self.start.add(addr) } as *const _;
             unsafe { std::ptr::read_unaligned(ptr) }
         }
@@ -37,22 +114,43 @@ impl ReplAppMemory for ExpectMemory {
     deref_number!(deref_f64, f64);
 
     fn deref_str(&self, addr: usize) -> &str {
-        const WIDTH: usize = 3 * std::mem::size_of::<usize>();
+        let width = self.ptr_width();
+        let total_width = 3 * width;
+
+        // Bounds are validated for the whole `total_width` header up front, so
+        // the plain `+` below (and the ones further down that stay within it)
+        // can't overflow or run past `end`.
+        self.assert_bounds(addr, total_width);
 
-        let last_byte_addr = addr + WIDTH - 1;
+        let last_byte_addr = addr + total_width - 1;
         let last_byte = self.deref_i8(last_byte_addr);
 
         let is_small = last_byte < 0;
 
         if is_small {
-            let ptr = unsafe { self.start.add(addr) };
-            let roc_str: &RocStr = unsafe { &*ptr.cast() };
+            // A small string stores its bytes inline in the header, with its
+            // length packed into the low 7 bits of the same discriminant
+            // byte we just read. Decoding it this way (instead of casting
+            // into the host's `RocStr`) keeps it target-width-aware: the
+            // host's `RocStr` is `3 * size_of::<usize>()` bytes, which isn't
+            // `total_width` when the target's pointer width differs from
+            // the host's.
+            let length = (last_byte as u8 & 0b0111_1111) as usize;
+
+            self.assert_bounds(addr, length);
+
+            unsafe {
+                let ptr = self.start.add(addr);
+                let slice = std::slice::from_raw_parts(ptr, length);
 
-            roc_str.as_str()
+                std::str::from_utf8_unchecked(slice)
+            }
         } else {
-            let offset = self.deref_usize(addr);
-            let length = self.deref_usize(addr + std::mem::size_of::<usize>());
-            let _capacity = self.deref_usize(addr + 2 * std::mem::size_of::<usize>());
+            let offset = self.deref_target_usize(addr);
+            let length = self.deref_target_usize(addr + width);
+            let _capacity = self.deref_target_usize(addr + 2 * width);
+
+            self.assert_bounds(offset, length);
 
             unsafe {
                 let ptr = self.start.add(offset);
@@ -81,6 +179,9 @@ impl<'a> ReplApp<'a> for ExpectReplApp<'a> {
         F: Fn(&'a Self::Memory, Return) -> Expr<'a>,
         Self::Memory: 'a,
     {
+        self.memory
+            .assert_bounds(self.offset, std::mem::size_of::<Return>());
+
         let result: Return = unsafe {
             let ptr = self.memory.start.add(self.offset);
             let ptr: *const Return = std::mem::transmute(ptr);
@@ -90,17 +191,29 @@ impl<'a> ReplApp<'a> for ExpectReplApp<'a> {
         transform(self.memory, result)
     }
 
-    fn call_function_returns_roc_list<F>(&mut self, main_fn_name: &str, transform: F) -> Expr<'a>
+    fn call_function_returns_roc_list<F>(&mut self, _main_fn_name: &str, transform: F) -> Expr<'a>
     where
         F: Fn(&'a Self::Memory, (usize, usize, usize)) -> Expr<'a>,
         Self::Memory: 'a,
     {
-        self.call_function(main_fn_name, transform)
+        // A `RocList`'s header is three pointer-sized fields (offset, length,
+        // capacity), so we can't just transmute it into a Rust `(usize, usize,
+        // usize)` tuple -- that assumes the host's `usize` width, which may not
+        // match the target's.
+        let width = self.memory.ptr_width();
+
+        self.memory.assert_bounds(self.offset, 3 * width);
+
+        let offset = self.memory.deref_target_usize(self.offset);
+        let length = self.memory.deref_target_usize(self.offset + width);
+        let capacity = self.memory.deref_target_usize(self.offset + 2 * width);
+
+        transform(self.memory, (offset, length, capacity))
     }
 
     fn call_function_returns_roc_str<T, F>(
         &mut self,
-        _target_info: TargetInfo,
+        target_info: TargetInfo,
         main_fn_name: &str,
         transform: F,
     ) -> T
@@ -108,7 +221,20 @@ impl<'a> ReplApp<'a> for ExpectReplApp<'a> {
         F: Fn(&'a Self::Memory, usize) -> T,
         Self::Memory: 'a,
     {
-        self.call_function_dynamic_size(main_fn_name, 24, transform)
+        // `ExpectMemory` already carries its own `target_info` (used for all
+        // the pointer-width math above); this parameter is handed to us
+        // separately by the `ReplApp` trait. Assert they agree rather than
+        // quietly trusting whichever one we happen to use, so the two don't
+        // drift if a caller ever passes a mismatched target.
+        debug_assert_eq!(
+            target_info, self.memory.target_info,
+            "call_function_returns_roc_str was given a different TargetInfo than the one \
+             ExpectMemory was constructed with"
+        );
+
+        let ret_bytes = 3 * self.memory.ptr_width();
+
+        self.call_function_dynamic_size(main_fn_name, ret_bytes, transform)
     }
 
     /// Run user code that returns a struct or union, whose size is provided as an argument
@@ -125,6 +251,94 @@ impl<'a> ReplApp<'a> for ExpectReplApp<'a> {
         F: Fn(&'a Self::Memory, usize) -> T,
         Self::Memory: 'a,
     {
+        self.memory.assert_bounds(self.offset, _ret_bytes);
+
         transform(self.memory, self.offset)
     }
+}
+
+impl<'a> ExpectReplApp<'a> {
+    /// Like `call_function`, but also threads the literal's original `Base`
+    /// through to `transform`, so an integer re-rendered from memory can keep
+    /// the hex/octal/binary/decimal form it was written in (e.g. `0xFF`)
+    /// instead of always flattening to decimal.
+    ///
+    /// `base` isn't something `self.memory` can recover on its own: it's
+    /// information about how the literal was *written*, which doesn't
+    /// survive into the compiled value's raw bytes. Callers that already
+    /// know it -- because they have the `Expr2`/`Content` the value
+    /// originated from -- pass it in explicitly; this just carries it the
+    /// rest of the way down to where the `Expr` literal gets rebuilt.
+    ///
+    /// This is scaffolding: nothing in this tree actually calls it yet.
+    /// The real call site needs to live wherever the base is first known
+    /// (jit_to_ast and friends, in the `roc_repl_eval` crate this one
+    /// depends on but doesn't contain), and isn't present in this
+    /// checkout to wire up.
+    pub(crate) fn call_function_with_base<Return, F>(
+        &mut self,
+        _main_fn_name: &str,
+        base: Base,
+        transform: F,
+    ) -> Expr<'a>
+    where
+        F: Fn(&'a ExpectMemory, Return, Base) -> Expr<'a>,
+        Return: 'a,
+    {
+        self.memory
+            .assert_bounds(self.offset, std::mem::size_of::<Return>());
+
+        let result: Return = unsafe {
+            let ptr = self.memory.start.add(self.offset);
+            let ptr: *const Return = std::mem::transmute(ptr);
+            ptr.read()
+        };
+
+        transform(self.memory, result, base)
+    }
+}
+
+#[test]
+fn check_bounds_accepts_reads_within_range() {
+    let bytes = [0u8; 16];
+    let memory = ExpectMemory::new(bytes.as_ptr(), bytes.len(), TargetInfo::default_x86_64());
+
+    assert!(memory.check_bounds(0, 16).is_ok());
+    assert!(memory.check_bounds(8, 8).is_ok());
+}
+
+#[test]
+fn check_bounds_rejects_reads_past_the_end() {
+    let bytes = [0u8; 16];
+    let memory = ExpectMemory::new(bytes.as_ptr(), bytes.len(), TargetInfo::default_x86_64());
+
+    assert!(memory.check_bounds(8, 9).is_err());
+    assert!(memory.check_bounds(16, 1).is_err());
+}
+
+#[test]
+fn check_bounds_rejects_overflowing_offsets_without_forming_a_pointer() {
+    let bytes = [0u8; 16];
+    let memory = ExpectMemory::new(bytes.as_ptr(), bytes.len(), TargetInfo::default_x86_64());
+
+    // These would be UB to compute with `.add()` before validating; check_bounds
+    // has to reject them using plain integer math instead.
+    assert!(memory.check_bounds(usize::MAX, 1).is_err());
+    assert!(memory.check_bounds(1, usize::MAX).is_err());
+}
+
+#[test]
+fn deref_str_decodes_small_strings_using_the_target_ptr_width() {
+    // A 32-bit target's small-string header is 3 * 4 = 12 bytes, not the
+    // host's `RocStr` size -- decoding this by casting into a host `RocStr`
+    // would read 12 bytes too many and pull the length from the wrong byte.
+    let total_width = 3 * 4;
+    let mut bytes = vec![0u8; total_width];
+    bytes[0] = b'h';
+    bytes[1] = b'i';
+    bytes[total_width - 1] = 0b1000_0010; // small-string marker, length 2
+
+    let memory = ExpectMemory::new(bytes.as_ptr(), bytes.len(), TargetInfo::default_x86_32());
+
+    assert_eq!(memory.deref_str(0), "hi");
 }
\ No newline at end of file
@@ -33,6 +33,21 @@ impl IntStyle {
             Base::Binary => Self::Binary,
         }
     }
+
+    /// Render `magnitude`'s digits in this style, e.g. `Hex` renders `255` as
+    /// `"0xFF"`. Used when reconstructing an integer literal from a value
+    /// read back out of memory, so an `expect` on `0xFF` round-trips to
+    /// `0xFF` in diagnostics instead of being flattened to `255`.
+    pub fn render(&self, magnitude: u128, is_negative: bool) -> String {
+        let sign = if is_negative { "-" } else { "" };
+
+        match self {
+            Self::Decimal => format!("{sign}{magnitude}"),
+            Self::Octal => format!("{sign}0o{magnitude:o}"),
+            Self::Hex => format!("{sign}0x{magnitude:X}"),
+            Self::Binary => format!("{sign}0b{magnitude:b}"),
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -47,12 +62,43 @@ pub enum IntVal {
     U8(u8),
 }
 
+impl IntVal {
+    /// Render this value's text in `style`'s base, preserving the base the
+    /// original literal was written in rather than always falling back to
+    /// decimal.
+    pub fn render(&self, style: IntStyle) -> String {
+        let (magnitude, is_negative) = match *self {
+            Self::I64(n) => (n.unsigned_abs() as u128, n.is_negative()),
+            Self::U64(n) => (n as u128, false),
+            Self::I32(n) => (n.unsigned_abs() as u128, n.is_negative()),
+            Self::U32(n) => (n as u128, false),
+            Self::I16(n) => (n.unsigned_abs() as u128, n.is_negative()),
+            Self::U16(n) => (n as u128, false),
+            Self::I8(n) => (n.unsigned_abs() as u128, n.is_negative()),
+            Self::U8(n) => (n as u128, false),
+        };
+
+        style.render(magnitude, is_negative)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FloatVal {
     F64(f64),
     F32(f32),
 }
 
+impl FloatVal {
+    /// Render this value's text, preserving the precision it was stored
+    /// with rather than always widening to `f64`'s `Display` output.
+    pub fn render(&self) -> String {
+        match self {
+            Self::F64(n) => format!("{n}"),
+            Self::F32(n) => format!("{n}"),
+        }
+    }
+}
+
 #[test]
 fn size_of_intval() {
     assert_eq!(std::mem::size_of::<IntVal>(), 16);
@@ -0,0 +1,548 @@
+//! A compact evaluator for `Expr2`, so the editor can run an expression (and
+//! render its `expect`s) without routing through the LLVM or dev backends.
+//!
+//! This mirrors a minimal register-based codegen backend: `compile` lowers an
+//! `Expr2` tree into a flat `Vec<Op>` using destination semantics -- each `Op`
+//! writes its result into the register at its own index in the array -- and
+//! `run` walks that array with a program counter, branching via `Jump` /
+//! `JumpUnless` instead of recursing into the tree.
+
+use crate::ast::{Expr2, FloatVal, IntStyle, IntVal};
+use crate::pool::{NodeId, Pool, PoolVec};
+use roc_module::low_level::LowLevel;
+use roc_types::subs::Variable;
+
+/// An index into the flat op/register array that `compile` produces. `Reg`s
+/// only ever point backwards (to an already-executed op), since every op's
+/// destination is its own position in the array.
+pub type Reg = usize;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(IntVal, IntStyle),
+    I128(i128, IntStyle),
+    U128(u128, IntStyle),
+    Float(FloatVal),
+    Str(String),
+    List(Vec<Value>),
+    Record(Vec<(String, Value)>),
+    Tag { name: String, args: Vec<Value> },
+    /// Produced when evaluation hits an `Expr2::RuntimeError`, or anything
+    /// else this evaluator doesn't cover yet -- mirrors `Expr2`'s own
+    /// "compiles, but will crash if reached" variant.
+    RuntimeError,
+}
+
+impl Value {
+    /// Render this value back to the literal text it would round-trip to,
+    /// honoring the base (and width) the original literal was written with,
+    /// rather than always flattening integers to decimal.
+    pub fn to_literal_text(&self) -> Option<String> {
+        match self {
+            Value::Int(number, style) => Some(number.render(*style)),
+            Value::I128(number, style) => {
+                Some(style.render(number.unsigned_abs(), number.is_negative()))
+            }
+            Value::U128(number, style) => Some(style.render(*number, false)),
+            Value::Float(number) => Some(number.render()),
+            Value::Str(string) => Some(string.clone()),
+            Value::List(_) | Value::Record(_) | Value::Tag { .. } | Value::RuntimeError => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Op {
+    LoadInt(IntVal, IntStyle),
+    LoadI128(i128, IntStyle),
+    LoadU128(u128, IntStyle),
+    LoadFloat(FloatVal),
+    LoadStr(String),
+    MakeList(Vec<Reg>),
+    MakeRecord(Vec<(String, Reg)>),
+    Access { record: Reg, field: String },
+    MakeTag { name: String, args: Vec<Reg> },
+    LowLevel { op: LowLevel, args: Vec<Reg> },
+    RuntimeError,
+
+    /// Take whichever of `a` or `b` actually executed -- used at the merge
+    /// point of an `If`, where only one side of the branch ran.
+    Phi(Reg, Reg),
+
+    /// Unconditional jump to an absolute op index.
+    Jump(usize),
+    /// If the value in `cond` is falsy, jump to `target`; otherwise fall
+    /// through to the next op. Doesn't produce a value of its own.
+    JumpUnless { cond: Reg, target: usize },
+}
+
+struct Compiler<'a> {
+    pool: &'a Pool,
+    ops: Vec<Op>,
+}
+
+impl<'a> Compiler<'a> {
+    fn push(&mut self, op: Op) -> Reg {
+        self.ops.push(op);
+        self.ops.len() - 1
+    }
+
+    fn compile(&mut self, expr_id: NodeId<Expr2>) -> Reg {
+        // `self.pool` is a plain `&'a Pool` (a `Copy` field), so re-borrowing
+        // it this way doesn't tie `expr`'s lifetime to `&mut self` -- we can
+        // still recurse into `self.compile`/`self.push` while holding it.
+        let pool = self.pool;
+        let expr = pool.get(expr_id);
+
+        self.compile_expr2(expr)
+    }
+
+    fn compile_expr2(&mut self, expr2: &Expr2) -> Reg {
+        let pool = self.pool;
+
+        match expr2 {
+            Expr2::SmallInt { number, style, .. } => self.push(Op::LoadInt(*number, *style)),
+            Expr2::I128 { number, style, .. } => self.push(Op::LoadI128(*number, *style)),
+            Expr2::U128 { number, style, .. } => self.push(Op::LoadU128(*number, *style)),
+            Expr2::Float { number, .. } => self.push(Op::LoadFloat(*number)),
+            Expr2::SmallStr(string) => self.push(Op::LoadStr(string.to_string())),
+            Expr2::Str(pool_str) => self.push(Op::LoadStr(pool.get_str(pool_str))),
+            Expr2::RuntimeError(..) => self.push(Op::RuntimeError),
+
+            Expr2::EmptyRecord => self.push(Op::MakeRecord(Vec::new())),
+
+            Expr2::List { elems, .. } => {
+                let elem_regs: Vec<Reg> = pool
+                    .iter_vec(elems)
+                    .map(|elem| self.compile_expr2(elem))
+                    .collect();
+
+                self.push(Op::MakeList(elem_regs))
+            }
+
+            Expr2::Record { fields, .. } => {
+                let field_regs: Vec<(String, Reg)> = pool
+                    .iter_vec(fields)
+                    .map(|(name, _var, field_expr_id)| {
+                        (pool.get_str(name), self.compile(*field_expr_id))
+                    })
+                    .collect();
+
+                self.push(Op::MakeRecord(field_regs))
+            }
+
+            Expr2::Access { field, expr, .. } => {
+                let record_reg = self.compile(*expr);
+                let field = pool.get_str(field);
+
+                self.push(Op::Access {
+                    record: record_reg,
+                    field,
+                })
+            }
+
+            Expr2::RunLowLevel { op, args, .. } => {
+                let op = *op;
+                let arg_regs: Vec<Reg> = pool
+                    .iter_vec(args)
+                    .map(|(_var, arg_id)| self.compile(*arg_id))
+                    .collect();
+
+                self.push(Op::LowLevel { op, args: arg_regs })
+            }
+
+            Expr2::GlobalTag {
+                name, arguments, ..
+            } => {
+                let name = pool.get_str(name);
+                let arg_regs: Vec<Reg> = pool
+                    .iter_vec(arguments)
+                    .map(|(_var, arg_id)| self.compile(*arg_id))
+                    .collect();
+
+                self.push(Op::MakeTag {
+                    name,
+                    args: arg_regs,
+                })
+            }
+
+            Expr2::PrivateTag {
+                name, arguments, ..
+            } => {
+                // Unlike `GlobalTag`, a private tag's name is a `Symbol`
+                // rather than a `PoolStr`, so there's no pool-local string to
+                // read; resolving it to the tag's actual source name needs
+                // the module's symbol interner, which this evaluator doesn't
+                // have access to. `Symbol`'s `Display` (unlike its `Debug`)
+                // is the closer approximation of that name.
+                let name = name.to_string();
+                let arg_regs: Vec<Reg> = pool
+                    .iter_vec(arguments)
+                    .map(|(_var, arg_id)| self.compile(*arg_id))
+                    .collect();
+
+                self.push(Op::MakeTag {
+                    name,
+                    args: arg_regs,
+                })
+            }
+
+            Expr2::If {
+                branches,
+                final_else,
+                ..
+            } => self.compile_if(branches, *final_else),
+
+            // `Var`/`Closure`/`Call` need an environment of bindings this
+            // compact evaluator doesn't carry, and `When` needs `Pattern2`
+            // (declared in `crate::pattern`, not checked into this tree) to
+            // test branches against the scrutinee -- both are out of scope
+            // here, same as `Expr2::RuntimeError` itself.
+            Expr2::Var(..)
+            | Expr2::LetRec { .. }
+            | Expr2::LetFunction { .. }
+            | Expr2::LetValue { .. }
+            | Expr2::Call { .. }
+            | Expr2::Closure { .. }
+            | Expr2::Accessor { .. }
+            | Expr2::Update { .. }
+            | Expr2::When { .. } => self.push(Op::RuntimeError),
+        }
+    }
+
+    /// Compiles every `(cond, body)` pair of an `if / else if / ... / else`
+    /// chain, starting at `branches[0]`.
+    fn compile_if(&mut self, branches: &PoolVec<(Expr2, Expr2)>, final_else: NodeId<Expr2>) -> Reg {
+        self.compile_if_branch(branches, 0, final_else)
+    }
+
+    /// Compiles `branches[index..]`, recursing into the next branch (or
+    /// `final_else`, once the branches are exhausted) for the `else` arm --
+    /// so `if a then 1 else if b then 2 else 3` lowers to nested
+    /// `JumpUnless`/`Phi` pairs instead of only ever looking at `branches[0]`.
+    fn compile_if_branch(
+        &mut self,
+        branches: &PoolVec<(Expr2, Expr2)>,
+        index: usize,
+        final_else: NodeId<Expr2>,
+    ) -> Reg {
+        let pool = self.pool;
+        let pair = pool.iter_vec(branches).nth(index);
+
+        let (cond, body) = match pair {
+            Some(pair) => pair,
+            None => return self.compile(final_else),
+        };
+
+        let cond_reg = self.compile_expr2(cond);
+        let jump_unless = self.push(Op::JumpUnless {
+            cond: cond_reg,
+            target: 0, // patched below
+        });
+
+        let then_reg = self.compile_expr2(body);
+        let jump_to_end = self.push(Op::Jump(0)); // patched below
+
+        let else_start = self.ops.len();
+        if let Op::JumpUnless { target, .. } = &mut self.ops[jump_unless] {
+            *target = else_start;
+        }
+
+        let else_reg = self.compile_if_branch(branches, index + 1, final_else);
+        let end = self.ops.len();
+        if let Op::Jump(target) = &mut self.ops[jump_to_end] {
+            *target = end;
+        }
+
+        self.push(Op::Phi(then_reg, else_reg))
+    }
+}
+
+/// Compile `expr_id` to a flat op array and run it to completion.
+pub fn eval_expr2(pool: &Pool, expr_id: NodeId<Expr2>) -> Value {
+    let mut compiler = Compiler {
+        pool,
+        ops: Vec::new(),
+    };
+    let result_reg = compiler.compile(expr_id);
+
+    run(&compiler.ops, result_reg)
+}
+
+/// Evaluate `expr_id` and render the result back to literal text, for
+/// inline `expect` output. Returns `None` for values that don't have a
+/// single-line literal form (lists, records, tags).
+///
+/// This is scaffolding, not wired into the editor's `expect` rendering yet:
+/// that path goes through `ExpectReplApp`/`ExpectMemory` in
+/// `crates/repl_expect`, a separate crate built around `roc_parse::ast::Expr`
+/// rather than this crate's `Expr2`/`Value`, with no shared call site in
+/// this tree to bridge the two. Call it directly once the editor has its
+/// own `expect` surface that owns a `Pool` and wants literal-style output.
+pub fn eval_expr2_to_text(pool: &Pool, expr_id: NodeId<Expr2>) -> Option<String> {
+    eval_expr2(pool, expr_id).to_literal_text()
+}
+
+fn run(ops: &[Op], result_reg: Reg) -> Value {
+    let mut registers: Vec<Option<Value>> = (0..ops.len()).map(|_| None).collect();
+    let mut pc = 0;
+
+    while pc < ops.len() {
+        match &ops[pc] {
+            Op::Jump(target) => {
+                pc = *target;
+                continue;
+            }
+            Op::JumpUnless { cond, target } => {
+                let cond_is_true = matches!(
+                    registers[*cond],
+                    Some(Value::Tag { ref name, .. }) if name == "True"
+                );
+
+                if !cond_is_true {
+                    pc = *target;
+                    continue;
+                }
+
+                pc += 1;
+                continue;
+            }
+            op => {
+                registers[pc] = Some(eval_op(op, &registers));
+                pc += 1;
+            }
+        }
+    }
+
+    registers[result_reg].take().unwrap_or(Value::RuntimeError)
+}
+
+fn eval_op(op: &Op, registers: &[Option<Value>]) -> Value {
+    match op {
+        Op::LoadInt(number, style) => Value::Int(*number, *style),
+        Op::LoadI128(number, style) => Value::I128(*number, *style),
+        Op::LoadU128(number, style) => Value::U128(*number, *style),
+        Op::LoadFloat(number) => Value::Float(*number),
+        Op::LoadStr(string) => Value::Str(string.clone()),
+        Op::MakeList(elems) => Value::List(
+            elems
+                .iter()
+                .map(|reg| registers[*reg].clone().unwrap_or(Value::RuntimeError))
+                .collect(),
+        ),
+        Op::MakeRecord(fields) => Value::Record(
+            fields
+                .iter()
+                .map(|(name, reg)| {
+                    (
+                        name.clone(),
+                        registers[*reg].clone().unwrap_or(Value::RuntimeError),
+                    )
+                })
+                .collect(),
+        ),
+        Op::Access { record, field } => match &registers[*record] {
+            Some(Value::Record(fields)) => fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, value)| value.clone())
+                .unwrap_or(Value::RuntimeError),
+            _ => Value::RuntimeError,
+        },
+        Op::MakeTag { name, args } => Value::Tag {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|reg| registers[*reg].clone().unwrap_or(Value::RuntimeError))
+                .collect(),
+        },
+        Op::LowLevel { op, args } => eval_low_level(*op, args, registers),
+        Op::RuntimeError => Value::RuntimeError,
+        Op::Phi(a, b) => registers[*a]
+            .clone()
+            .or_else(|| registers[*b].clone())
+            .unwrap_or(Value::RuntimeError),
+        Op::Jump(_) | Op::JumpUnless { .. } => unreachable!("handled in run's dispatch loop"),
+    }
+}
+
+fn eval_low_level(op: LowLevel, args: &[Reg], registers: &[Option<Value>]) -> Value {
+    let arg_values: Vec<&Value> = args
+        .iter()
+        .filter_map(|reg| registers[*reg].as_ref())
+        .collect();
+
+    match (op, arg_values.as_slice()) {
+        (LowLevel::NumAdd, [Value::Int(IntVal::I64(a), style), Value::Int(IntVal::I64(b), _)]) => {
+            Value::Int(IntVal::I64(a + b), *style)
+        }
+        (LowLevel::NumSub, [Value::Int(IntVal::I64(a), style), Value::Int(IntVal::I64(b), _)]) => {
+            Value::Int(IntVal::I64(a - b), *style)
+        }
+        (LowLevel::NumMul, [Value::Int(IntVal::I64(a), style), Value::Int(IntVal::I64(b), _)]) => {
+            Value::Int(IntVal::I64(a * b), *style)
+        }
+        // Other `LowLevel`s (and other `IntVal` widths) aren't wired up yet;
+        // this evaluator only needs to cover the arithmetic ops exercised by
+        // `expect`s today.
+        _ => Value::RuntimeError,
+    }
+}
+
+#[test]
+fn run_threads_a_value_through_a_low_level_op() {
+    let ops = vec![
+        Op::LoadInt(IntVal::I64(40), IntStyle::Decimal),
+        Op::LoadInt(IntVal::I64(2), IntStyle::Decimal),
+        Op::LowLevel {
+            op: LowLevel::NumAdd,
+            args: vec![0, 1],
+        },
+    ];
+
+    match run(&ops, 2) {
+        Value::Int(IntVal::I64(42), IntStyle::Decimal) => {}
+        other => panic!("expected Int(42), got {other:?}"),
+    }
+}
+
+#[test]
+fn run_takes_the_then_branch_when_the_condition_is_true() {
+    let ops = vec![
+        Op::MakeTag {
+            name: "True".to_string(),
+            args: Vec::new(),
+        }, // 0: cond
+        Op::JumpUnless {
+            cond: 0,
+            target: 4,
+        }, // 1
+        Op::LoadInt(IntVal::I64(1), IntStyle::Decimal), // 2: then
+        Op::Jump(5),                                    // 3
+        Op::LoadInt(IntVal::I64(2), IntStyle::Decimal), // 4: else
+        Op::Phi(2, 4),                                  // 5
+    ];
+
+    match run(&ops, 5) {
+        Value::Int(IntVal::I64(1), _) => {}
+        other => panic!("expected the then-branch's value, got {other:?}"),
+    }
+}
+
+#[test]
+fn run_takes_the_else_branch_when_the_condition_is_false() {
+    let ops = vec![
+        Op::MakeTag {
+            name: "False".to_string(),
+            args: Vec::new(),
+        }, // 0: cond
+        Op::JumpUnless {
+            cond: 0,
+            target: 4,
+        }, // 1
+        Op::LoadInt(IntVal::I64(1), IntStyle::Decimal), // 2: then
+        Op::Jump(5),                                    // 3
+        Op::LoadInt(IntVal::I64(2), IntStyle::Decimal), // 4: else
+        Op::Phi(2, 4),                                  // 5
+    ];
+
+    match run(&ops, 5) {
+        Value::Int(IntVal::I64(2), _) => {}
+        other => panic!("expected the else-branch's value, got {other:?}"),
+    }
+}
+
+#[test]
+fn access_looks_up_a_field_by_name() {
+    let ops = vec![
+        Op::LoadInt(IntVal::I64(1), IntStyle::Decimal),
+        Op::LoadInt(IntVal::I64(2), IntStyle::Decimal),
+        Op::MakeRecord(vec![("a".to_string(), 0), ("b".to_string(), 1)]),
+        Op::Access {
+            record: 2,
+            field: "b".to_string(),
+        },
+    ];
+
+    match run(&ops, 3) {
+        Value::Int(IntVal::I64(2), _) => {}
+        other => panic!("expected field \"b\"'s value, got {other:?}"),
+    }
+}
+
+#[test]
+fn to_literal_text_preserves_the_original_int_style() {
+    let value = Value::Int(IntVal::U8(255), IntStyle::Hex);
+
+    assert_eq!(value.to_literal_text().as_deref(), Some("0xFF"));
+}
+
+#[test]
+fn to_literal_text_returns_none_for_compound_values() {
+    assert_eq!(Value::List(Vec::new()).to_literal_text(), None);
+}
+
+#[test]
+fn compile_if_falls_through_every_else_if_branch() {
+    // if False then 1 else if True then 2 else 3
+    //
+    // Exercises `compile`/`compile_if` against a real multi-branch
+    // `Expr2::If`, unlike the hand-built `Op` arrays above: a compiler that
+    // only looked at `branches[0]` would jump straight to `final_else` (3)
+    // as soon as the first condition is false, skipping the `else if` arm.
+    let mut pool = Pool::with_capacity(64);
+
+    let false_tag = {
+        let name = pool.add_str("False").unwrap();
+        let arguments = pool
+            .add_vec(Vec::<(Variable, NodeId<Expr2>)>::new())
+            .unwrap();
+        Expr2::GlobalTag {
+            name,
+            variant_var: Variable::new(0),
+            ext_var: Variable::new(0),
+            arguments,
+        }
+    };
+
+    let true_tag = {
+        let name = pool.add_str("True").unwrap();
+        let arguments = pool
+            .add_vec(Vec::<(Variable, NodeId<Expr2>)>::new())
+            .unwrap();
+        Expr2::GlobalTag {
+            name,
+            variant_var: Variable::new(0),
+            ext_var: Variable::new(0),
+            arguments,
+        }
+    };
+
+    let int_expr = |pool: &mut Pool, n: i64| Expr2::SmallInt {
+        number: IntVal::I64(n),
+        var: Variable::new(0),
+        style: IntStyle::Decimal,
+        text: pool.add_str(&n.to_string()).unwrap(),
+    };
+
+    let one = int_expr(&mut pool, 1);
+    let two = int_expr(&mut pool, 2);
+    let three = int_expr(&mut pool, 3);
+
+    let branches = pool.add_vec(vec![(false_tag, one), (true_tag, two)]).unwrap();
+    let final_else = pool.add(three).unwrap();
+
+    let if_expr = pool
+        .add(Expr2::If {
+            cond_var: Variable::new(0),
+            expr_var: Variable::new(0),
+            branches,
+            final_else,
+        })
+        .unwrap();
+
+    match eval_expr2(&pool, if_expr) {
+        Value::Int(IntVal::I64(2), _) => {}
+        other => panic!("expected the else-if branch's value (2), got {other:?}"),
+    }
+}
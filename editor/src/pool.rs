@@ -0,0 +1,394 @@
+use std::alloc::{alloc, dealloc, Layout};
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+
+/// Every node in the pool is the same fixed size, matching `Expr2` (the
+/// largest thing we store). See the `size_of_expr` test in `ast.rs`.
+pub const NODE_BYTES: usize = 32;
+
+type NodeBytes = [MaybeUninit<u8>; NODE_BYTES];
+
+/// A slot's generation is bumped every time it's freed, so a stale `NodeId`
+/// minted before the free can be told apart from the fresh one handed out to
+/// whatever gets allocated into the recycled slot next.
+type Generation = u32;
+
+/// An arena of fixed-size nodes, indexed by `NodeId`.
+///
+/// Allocation is a bump allocator over a flat buffer, same as before. What's
+/// new is that freed slots go onto an intrusive free list instead of being
+/// abandoned: `alloc_slot` pops a reclaimed slot before bumping the
+/// high-water mark, so discarding and re-adding individual nodes via
+/// `add`/`free` doesn't march `num_nodes` straight to `capacity`.
+///
+/// That recycling is per-slot, though: `add_vec` (which backs every
+/// `PoolVec` -- list elems, record fields, call/tag args, if-branches, and
+/// so on) needs a *contiguous* run of slots, and doesn't scan the free list
+/// for one; it only ever bump-allocates. So a session that mostly discards
+/// and rebuilds compound subtrees, rather than single nodes, still marches
+/// towards `RanOutOfNodeIds` -- freeing a `PoolVec` reclaims its slots for
+/// `add`, not for a later `add_vec`.
+pub struct Pool {
+    nodes: *mut NodeBytes,
+    generations: Vec<Generation>,
+    free_nodes: Vec<u32>,
+    num_nodes: u32,
+    capacity: u32,
+}
+
+impl Pool {
+    pub fn with_capacity(capacity: u32) -> Self {
+        let layout = Layout::array::<NodeBytes>(capacity as usize).unwrap();
+        let nodes = unsafe { alloc(layout) as *mut NodeBytes };
+
+        Self {
+            nodes,
+            generations: vec![0; capacity as usize],
+            free_nodes: Vec::new(),
+            num_nodes: 0,
+            capacity,
+        }
+    }
+
+    /// Store `node` in a fresh or recycled slot, returning the `NodeId` it was
+    /// minted with. Returns `None` if the pool is full and has no recycled
+    /// slots left to hand out; the caller surfaces that as
+    /// `Problem::RanOutOfNodeIds`.
+    pub fn add<T>(&mut self, node: T) -> Option<NodeId<T>> {
+        debug_assert!(std::mem::size_of::<T>() <= NODE_BYTES);
+
+        let index = self.alloc_slot()?;
+
+        unsafe {
+            let node_ptr = self.nodes.add(index as usize) as *mut T;
+            node_ptr.write(node);
+        }
+
+        Some(NodeId {
+            index,
+            generation: self.generations[index as usize],
+            _phantom: PhantomData,
+        })
+    }
+
+    fn alloc_slot(&mut self) -> Option<u32> {
+        if let Some(index) = self.free_nodes.pop() {
+            return Some(index);
+        }
+
+        if self.num_nodes == self.capacity {
+            return None;
+        }
+
+        let index = self.num_nodes;
+        self.num_nodes += 1;
+
+        Some(index)
+    }
+
+    /// Reclaim a node's slot so it can be handed out again by a later `add`.
+    /// Bumps the slot's generation so any other `NodeId`s still pointing at
+    /// it are recognized as stale rather than silently aliasing whatever gets
+    /// allocated into the recycled slot next.
+    pub fn free<T>(&mut self, node_id: NodeId<T>) {
+        let index = node_id.index as usize;
+
+        // This has to hold in release builds too, not just debug: freeing a
+        // stale `NodeId` twice would otherwise push the same index onto
+        // `free_nodes` twice, so two unrelated later `add` calls could each
+        // pop one of the duplicates and write into the *same* slot, with
+        // `get`/`try_get` never catching the aliasing.
+        assert_eq!(
+            self.generations[index], node_id.generation,
+            "tried to free a NodeId from a generation that's already been recycled"
+        );
+
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_nodes.push(node_id.index);
+    }
+
+    /// Reclaim every slot backing a `PoolVec`.
+    pub fn free_vec<T>(&mut self, vec: &PoolVec<T>) {
+        for index in vec.first_node_id.index..(vec.first_node_id.index + vec.len) {
+            // Every slot in the run was minted together (see `add_vec`), so
+            // they share `first_node_id`'s generation -- using it here (and
+            // not the slot's *current* `self.generations[index]`) is what
+            // lets `free` detect a double free: the second `free_vec` call
+            // on the same `PoolVec` still carries the old generation, which
+            // by then disagrees with what `free` bumped it to.
+            let node_id = NodeId {
+                index,
+                generation: vec.first_node_id.generation,
+                _phantom: PhantomData,
+            };
+
+            self.free(node_id);
+        }
+    }
+
+    pub fn get<T>(&self, node_id: NodeId<T>) -> &T {
+        self.try_get(node_id).expect(
+            "Tried to dereference a NodeId whose slot has since been freed and recycled",
+        )
+    }
+
+    pub fn get_mut<T>(&mut self, node_id: NodeId<T>) -> &mut T {
+        self.try_get_mut(node_id).expect(
+            "Tried to dereference a NodeId whose slot has since been freed and recycled",
+        )
+    }
+
+    /// Like `get`, but returns `None` instead of panicking if `node_id` was
+    /// minted for a slot that's since been freed and recycled.
+    pub fn try_get<T>(&self, node_id: NodeId<T>) -> Option<&T> {
+        if self.generations[node_id.index as usize] != node_id.generation {
+            return None;
+        }
+
+        let node_ptr = unsafe { self.nodes.add(node_id.index as usize) as *const T };
+
+        Some(unsafe { &*node_ptr })
+    }
+
+    pub fn try_get_mut<T>(&mut self, node_id: NodeId<T>) -> Option<&mut T> {
+        if self.generations[node_id.index as usize] != node_id.generation {
+            return None;
+        }
+
+        let node_ptr = unsafe { self.nodes.add(node_id.index as usize) as *mut T };
+
+        Some(unsafe { &mut *node_ptr })
+    }
+
+    /// Store `items` in a contiguous run of fresh slots, returning the
+    /// `PoolVec` it was minted with. Unlike `add`, this always bumps the
+    /// high-water mark rather than drawing from the free list, since a
+    /// `PoolVec`'s slots need to be contiguous. Returns `None` if there
+    /// isn't enough room left.
+    pub fn add_vec<T, I>(&mut self, items: I) -> Option<PoolVec<T>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let items = items.into_iter();
+        let len = items.len() as u32;
+
+        if self.num_nodes.checked_add(len)? > self.capacity {
+            return None;
+        }
+
+        let first_index = self.num_nodes;
+        // An empty vec on a full pool has no slot at `first_index`; slots at
+        // or above the high-water mark have never been freed, so are on 0.
+        let generation = self
+            .generations
+            .get(first_index as usize)
+            .copied()
+            .unwrap_or(0);
+
+        for (offset, item) in items.enumerate() {
+            debug_assert!(std::mem::size_of::<T>() <= NODE_BYTES);
+
+            let index = first_index + offset as u32;
+            let node_ptr = unsafe { self.nodes.add(index as usize) as *mut T };
+
+            unsafe { node_ptr.write(item) };
+        }
+
+        self.num_nodes += len;
+
+        Some(PoolVec {
+            first_node_id: NodeId {
+                index: first_index,
+                generation,
+                _phantom: PhantomData,
+            },
+            len,
+        })
+    }
+
+    /// Iterate over the elements of a `PoolVec` in order.
+    pub fn iter_vec<T>(&self, vec: &PoolVec<T>) -> impl Iterator<Item = &T> + '_ {
+        let first_index = vec.first_node_id.index;
+
+        (0..vec.len).map(move |offset| {
+            let index = first_index + offset;
+            let node_ptr = unsafe { self.nodes.add(index as usize) as *const T };
+
+            unsafe { &*node_ptr }
+        })
+    }
+
+    /// Copy `string`'s bytes into the pool, returning the `PoolStr` it was
+    /// stored as.
+    pub fn add_str(&mut self, string: &str) -> Option<PoolStr> {
+        let first_node_id = self.add_vec(string.bytes())?.first_node_id;
+
+        Some(PoolStr {
+            first_node_id,
+            len: string.len() as u32,
+        })
+    }
+
+    /// Read a `PoolStr`'s bytes back out as an owned `String`.
+    pub fn get_str(&self, pool_str: &PoolStr) -> String {
+        let first_index = pool_str.first_node_id.index;
+
+        let bytes: Vec<u8> = (0..pool_str.len)
+            .map(|offset| {
+                let index = first_index + offset;
+                unsafe { *(self.nodes.add(index as usize) as *const u8) }
+            })
+            .collect();
+
+        String::from_utf8(bytes).unwrap_or_default()
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        let layout = Layout::array::<NodeBytes>(self.capacity as usize).unwrap();
+
+        unsafe { dealloc(self.nodes as *mut u8, layout) };
+    }
+}
+
+/// A reference to a node of type `T` in a `Pool`, tagged with the generation
+/// the slot was on when this id was minted. A `NodeId` outliving a `free` of
+/// its slot is a stale reference, not a dangling one: looking it up reports
+/// that instead of aliasing whatever got allocated into the slot next.
+pub struct NodeId<T> {
+    index: u32,
+    generation: Generation,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> std::fmt::Debug for NodeId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NodeId({}, gen {})", self.index, self.generation)
+    }
+}
+
+impl<T> Copy for NodeId<T> {}
+
+impl<T> Clone for NodeId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for NodeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for NodeId<T> {}
+
+/// A contiguous run of `len` pool slots, starting at `first_node_id`.
+#[derive(Debug)]
+pub struct PoolVec<T> {
+    first_node_id: NodeId<T>,
+    len: u32,
+}
+
+impl<T> Copy for PoolVec<T> {}
+
+impl<T> Clone for PoolVec<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> ShallowClone for PoolVec<T> {
+    fn shallow_clone(&self) -> Self {
+        *self
+    }
+}
+
+/// A string stored inline in the pool, one byte per slot starting at
+/// `first_node_id`.
+#[derive(Debug, Copy, Clone)]
+pub struct PoolStr {
+    first_node_id: NodeId<u8>,
+    len: u32,
+}
+
+impl ShallowClone for PoolStr {
+    fn shallow_clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Like `Clone`, but only clones the node itself and not anything it refers
+/// to by `NodeId` -- the pool slots those ids point to are shared with the
+/// original.
+pub trait ShallowClone {
+    fn shallow_clone(&self) -> Self;
+}
+
+#[test]
+fn add_then_get_roundtrips() {
+    let mut pool = Pool::with_capacity(4);
+    let id = pool.add(42u8).unwrap();
+
+    assert_eq!(*pool.get(id), 42);
+}
+
+#[test]
+fn free_then_try_get_returns_none() {
+    let mut pool = Pool::with_capacity(4);
+    let id = pool.add(42u8).unwrap();
+    pool.free(id);
+
+    assert_eq!(pool.try_get(id), None);
+}
+
+#[test]
+fn free_recycles_the_slot_for_a_later_add() {
+    let mut pool = Pool::with_capacity(1);
+    let first = pool.add(1u8).unwrap();
+    pool.free(first);
+
+    let second = pool.add(2u8).unwrap();
+
+    assert_eq!(*pool.get(second), 2);
+}
+
+#[test]
+#[should_panic(expected = "already been recycled")]
+fn freeing_the_same_node_id_twice_panics_instead_of_silently_double_freeing() {
+    let mut pool = Pool::with_capacity(4);
+    let id = pool.add(1u8).unwrap();
+
+    pool.free(id);
+    pool.free(id);
+}
+
+#[test]
+#[should_panic(expected = "already been recycled")]
+fn free_vec_twice_on_the_same_pool_vec_panics_instead_of_silently_double_freeing() {
+    let mut pool = Pool::with_capacity(4);
+    let vec = pool.add_vec([1u8, 2, 3]).unwrap();
+
+    pool.free_vec(&vec);
+    pool.free_vec(&vec);
+}
+
+#[test]
+fn add_vec_then_iter_vec_roundtrips_in_order() {
+    let mut pool = Pool::with_capacity(4);
+    let vec = pool.add_vec([10u8, 20, 30]).unwrap();
+
+    let collected: Vec<u8> = pool.iter_vec(&vec).copied().collect();
+
+    assert_eq!(collected, vec![10, 20, 30]);
+}
+
+#[test]
+fn add_str_then_get_str_roundtrips() {
+    let mut pool = Pool::with_capacity(8);
+    let pool_str = pool.add_str("hi there").unwrap();
+
+    assert_eq!(pool.get_str(&pool_str), "hi there");
+}